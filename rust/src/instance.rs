@@ -1,4 +1,6 @@
+use std::collections::HashMap;
 use std::ops::Range;
+use std::sync::{Mutex, OnceLock};
 use jni::descriptors::Desc;
 // This is the interface to the JVM that we'll
 // call the majority of our methods on.
@@ -6,26 +8,78 @@ use jni::JNIEnv;
 // These objects are what you should use as arguments to your native function.
 // They carry extra lifetime information to prevent them escaping this context
 // and getting used after being GC'd.
-use jni::objects::JValue;
+use jni::objects::{GlobalRef, JClass, JValue};
 // This is just a pointer. We'll be returning it from our function.
 // We can't return one of the objects with lifetime information because the
 // lifetime checker won't let us.
-use jni::sys::{jbyteArray, jint, jlong, jlongArray, jstring};
+use jni::sys::{jbyteArray, jint, jlong, jlongArray, jobject, jstring};
 use wasmer::{AsStoreMut, Function, FunctionType, Imports, Instance, RuntimeError, Store, Type, Value};
+use wasmer_middlewares::metering::{self, MeteringPoints};
 
 use crate::{StringErr};
 use crate::rp::Rp;
-use crate::utils::{JNIUtil, ToVmType};
+use crate::utils::JNIUtil;
+
+/// Decodes the raw `i64`-encoded values crossing the JNI boundary into wasm
+/// `Value`s according to the declared types, bit-reinterpreting floats
+/// (`f32::from_bits`/`f64::from_bits`) rather than truncating them the way a
+/// plain `as` cast would.
+fn values_from_i64(types: &[Type], raw: &[i64]) -> Result<Vec<Value>, String> {
+    if types.len() != raw.len() {
+        return Err(format!("expected {} values, got {}", types.len(), raw.len()));
+    }
+
+    types.iter().zip(raw.iter()).map(|(ty, v)| match ty {
+        Type::I32 => Ok(Value::I32(*v as i32)),
+        Type::I64 => Ok(Value::I64(*v)),
+        Type::F32 => Ok(Value::F32(f32::from_bits(*v as u32))),
+        Type::F64 => Ok(Value::F64(f64::from_bits(*v as u64))),
+        other => Err(format!("unsupported param type: {:?}", other)),
+    }).collect()
+}
+
+/// Inverse of [`values_from_i64`]: encodes wasm `Value`s back into the `i64`
+/// wire format, bit-reinterpreting floats so the other side can reconstruct
+/// them losslessly instead of seeing a generic "unsupported return type".
+fn values_to_i64(values: &[Value]) -> Result<Vec<i64>, String> {
+    values.iter().map(|v| match v {
+        Value::I32(x) => Ok(*x as i64),
+        Value::I64(x) => Ok(*x),
+        Value::F32(x) => Ok(x.to_bits() as i64),
+        Value::F64(x) => Ok(x.to_bits() as i64),
+        other => Err(format!("unsupported return type: {:?}", other)),
+    }).collect()
+}
+
+fn default_if_empty(name: &str) -> &str {
+    if name.is_empty() { "memory" } else { name }
+}
+
+/// Resolves the memory export name a Java caller passed in, falling back to
+/// `"memory"` when they passed null/empty for backward compatibility with
+/// single-memory modules.
+fn memory_export_name(env: &JNIEnv, name: jstring) -> Result<String, StringErr> {
+    if name.is_null() {
+        return Ok("memory".to_string());
+    }
+
+    let name = env.get_string(name.into())?;
+    let name = name.to_str()?;
+    Ok(default_if_empty(name).to_string())
+}
 
 pub fn get_memory(
     env: JNIEnv,
     descriptor: jlong,
+    name: jstring,
     off: jint,
     len: jint,
 ) -> Result<jbyteArray, StringErr> {
     unsafe {
+        let name = memory_export_name(&env, name)?;
         let ins = crate::get_ins_by_id(descriptor as usize);
-        let mem = ins.0.exports.get_memory("memory")?;
+        let mem = ins.0.exports.get_memory(&name)
+            .map_err(|_| StringErr(format!("no memory export named '{}'", name)))?;
         let view = mem.view(&ins.1);
         if (off + len) > view.data_size() as i32 || off < 0 || len < 0 {
             return Err(StringErr("memory access overflow".into()));
@@ -37,11 +91,13 @@ pub fn get_memory(
     }
 }
 
-pub fn set_memory(env: JNIEnv, descriptor: jlong, off: jint, buf: jbyteArray) -> Result<(), StringErr> {
+pub fn set_memory(env: JNIEnv, descriptor: jlong, name: jstring, off: jint, buf: jbyteArray) -> Result<(), StringErr> {
     unsafe {
+        let name = memory_export_name(&env, name)?;
         let ins = crate::get_ins_by_id(descriptor as usize);
         let bytes = env.convert_byte_array(buf)?;
-        let mem = ins.0.exports.get_memory("memory")?;
+        let mem = ins.0.exports.get_memory(&name)
+            .map_err(|_| StringErr(format!("no memory export named '{}'", name)))?;
         let view = mem.view(&ins.1);
 
         if (off as usize + bytes.len()) as u64 > view.data_size() {
@@ -55,6 +111,99 @@ pub fn set_memory(env: JNIEnv, descriptor: jlong, off: jint, buf: jbyteArray) ->
     }
 }
 
+/// Returns the names of every memory export on `descriptor`'s instance, so
+/// callers can discover what to pass to [`get_memory`]/[`set_memory`]/
+/// [`get_memory_buffer`] on modules that export their memory under a
+/// non-default name or export more than one (as the multi-memory proposal
+/// allows).
+pub fn list_memories(env: JNIEnv, descriptor: jlong) -> Result<jni::sys::jobjectArray, StringErr> {
+    unsafe {
+        let ins = crate::get_ins_by_id(descriptor as usize);
+        let names: Vec<&str> = ins.0.exports.iter().memories().map(|(name, _)| name.as_str()).collect();
+
+        let string_class = env.find_class("java/lang/String")?;
+        let array = env.new_object_array(names.len() as jint, string_class, env.new_string("")?)?;
+        for (i, name) in names.iter().enumerate() {
+            env.set_object_array_element(array, i as jint, env.new_string(name)?)?;
+        }
+        Ok(array)
+    }
+}
+
+/// Builds the gas-metering middleware to install on a module at compile
+/// time: a uniform per-operator cost and a starting point budget that
+/// `execute` debits from, and that can be topped up later via
+/// [`set_remaining_points`]. The compile path must also call [`mark_metered`]
+/// once the resulting module has been instantiated, so this file knows it's
+/// safe to touch the metering global for that instance — `wasmer_middlewares`
+/// panics if asked for remaining points on an instance that was never
+/// compiled with this middleware.
+pub fn metering_middleware(initial_points: u64) -> std::sync::Arc<wasmer_middlewares::Metering<fn(&wasmer::wasmparser::Operator) -> u64>> {
+    fn cost_function(_operator: &wasmer::wasmparser::Operator) -> u64 {
+        1
+    }
+    std::sync::Arc::new(wasmer_middlewares::Metering::new(initial_points, cost_function))
+}
+
+/// Instances whose module was compiled with [`metering_middleware`]
+/// installed. `wasmer_middlewares::metering::{get,set}_remaining_points`
+/// panic internally when called against an instance that lacks the metering
+/// global, so every other function in this file must check membership here
+/// before calling into that module.
+fn metered_instances() -> &'static Mutex<std::collections::HashSet<usize>> {
+    static METERED: OnceLock<Mutex<std::collections::HashSet<usize>>> = OnceLock::new();
+    METERED.get_or_init(|| Mutex::new(std::collections::HashSet::new()))
+}
+
+/// Records that `descriptor`'s instance was instantiated from a module
+/// compiled with [`metering_middleware`]. Must be called once, right after
+/// instantiation, by whatever compiles and instantiates the module; until
+/// it is, [`set_remaining_points`]/[`get_remaining_points`] and `execute`'s
+/// out-of-gas detection treat the instance as unmetered.
+pub fn mark_metered(descriptor: jlong) {
+    metered_instances().lock().unwrap().insert(descriptor as usize);
+}
+
+fn is_metered(descriptor: usize) -> bool {
+    metered_instances().lock().unwrap().contains(&descriptor)
+}
+
+/// Tops up (or lowers) the remaining metering points for `descriptor`,
+/// allowing a guest that previously ran out of gas to be resumed on a later
+/// `execute` call against the same instance.
+pub fn set_remaining_points(descriptor: jlong, points: jlong) -> Result<(), StringErr> {
+    if !is_metered(descriptor as usize) {
+        return Err(StringErr("instance was not compiled with metering installed".into()));
+    }
+    unsafe {
+        let mut ins = crate::get_ins_by_id(descriptor as usize);
+        metering::set_remaining_points(&mut ins.1, &ins.0, points as u64);
+    }
+    Ok(())
+}
+
+/// Returns the remaining metering points for `descriptor`, or `-1` if the
+/// budget has been exhausted. `-1` is a distinct sentinel from a legitimate
+/// budget of `0`: the latter means execution stopped with points to spare
+/// down to the wire, the former means it actually trapped on exhaustion and
+/// needs [`set_remaining_points`] before it can resume. A remaining count is
+/// saturated at `i64::MAX` rather than cast as-is: `set_remaining_points`
+/// accepts the full `u64` range, and an unchecked `as jlong` on a budget
+/// above `i64::MAX` would wrap into negative territory and could land
+/// exactly on `-1`, misreporting a well-funded instance as exhausted.
+pub fn get_remaining_points(descriptor: jlong) -> Result<jlong, StringErr> {
+    if !is_metered(descriptor as usize) {
+        return Err(StringErr("instance was not compiled with metering installed".into()));
+    }
+    unsafe {
+        let mut ins = crate::get_ins_by_id(descriptor as usize);
+        Ok(match metering::get_remaining_points(&mut ins.1, &ins.0) {
+            MeteringPoints::Remaining(points) => points.min(jlong::MAX as u64) as jlong,
+            MeteringPoints::Exhausted => -1,
+        })
+    }
+}
+
 pub fn close(env: JNIEnv, descriptor: jlong) -> Result<(), StringErr> {
     unsafe {
         let mut ins: Rp<Instance> = (descriptor as usize).into();
@@ -64,33 +213,203 @@ pub fn close(env: JNIEnv, descriptor: jlong) -> Result<(), StringErr> {
         }
         ins.drop();
     }
+    let descriptor = descriptor as usize;
+    // Drops the cached global refs to each host import's class, releasing
+    // them now that no closure captured here will ever be called again.
+    host_class_refs().lock().unwrap().remove(&descriptor);
+    remove_memory_tracking(descriptor);
+    metered_instances().lock().unwrap().remove(&descriptor);
     Ok(())
 }
 
+/// Global refs to the Java classes backing each instance's host imports,
+/// keyed by instance descriptor, so `close` can release them once the
+/// instance (and the closures that captured them) is torn down.
+fn host_class_refs() -> &'static Mutex<HashMap<usize, Vec<GlobalRef>>> {
+    static REFS: OnceLock<Mutex<HashMap<usize, Vec<GlobalRef>>>> = OnceLock::new();
+    REFS.get_or_init(|| Mutex::new(HashMap::new()))
+}
 
-pub fn create_host(store: &mut wasmer::Store, sig: (Vec<Type>, Vec<Type>), jvm: jni::JavaVM, ins: jint, host_id: jint) -> Function {
-    let host_function_signature = FunctionType::new(sig.0.clone(), sig.1.clone());
-    Function::new(store, &host_function_signature, move |_args| {
-        let ret_types = sig.1.clone();
-        let env: JNIEnv = as_rt!(jvm.get_env());
-        let v = as_i64_vec!(_args, RuntimeError::new("unexpected param type"));
-        let arr = env.call_static_method("com/archeros/wasmer/Natives", "onHostFunction", "(II[J)[J", &[
-            JValue::Int(ins),
-            JValue::Int(host_id),
-            JValue::Object(as_rt!(env.slice_to_jlong_array(&v)).into()),
-        ],
-        );
-
-        let arr = as_rt!(arr);
-        let o = match arr {
-            JValue::Object(o) => o,
-            _ => return Err(RuntimeError::new("unexpected return type")),
-        };
+/// Generation counter per `(instance, memory export name)`, bumped every
+/// time that memory is observed to have grown. A direct `ByteBuffer` handed
+/// out by [`get_memory_buffer`] is only valid for the generation it was
+/// fetched under: `memory.grow` can reallocate the backing storage, and a
+/// buffer pointing at the old allocation would be a use-after-free. This is
+/// tracked per memory name rather than per instance because a module can
+/// export more than one memory (or export it under a non-default name), and
+/// each grows independently.
+fn mem_generations() -> &'static Mutex<HashMap<(usize, String), u64>> {
+    static GENS: OnceLock<Mutex<HashMap<(usize, String), u64>>> = OnceLock::new();
+    GENS.get_or_init(|| Mutex::new(HashMap::new()))
+}
 
-        let v = env.jlong_array_to_vec(o.into_inner());
-        let v = as_rt!(v);
-        ret_types.convert(v)
-    })
+/// Last observed size per `(instance, memory export name)`, used by
+/// [`track_memory_growth`] to detect a `memory.grow` that happened during
+/// the most recent `execute`.
+fn mem_sizes() -> &'static Mutex<HashMap<(usize, String), u64>> {
+    static SIZES: OnceLock<Mutex<HashMap<(usize, String), u64>>> = OnceLock::new();
+    SIZES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn track_memory_growth(descriptor: usize, name: &str, current_size: u64) {
+    let mut sizes = mem_sizes().lock().unwrap();
+    let key = (descriptor, name.to_string());
+    if let Some(&prev) = sizes.get(&key) {
+        if current_size > prev {
+            *mem_generations().lock().unwrap().entry(key.clone()).or_insert(0) += 1;
+        }
+    }
+    sizes.insert(key, current_size);
+}
+
+fn remove_memory_tracking(descriptor: usize) {
+    mem_sizes().lock().unwrap().retain(|(d, _), _| *d != descriptor);
+    mem_generations().lock().unwrap().retain(|(d, _), _| *d != descriptor);
+}
+
+/// Returns the current memory generation for the `name` export of
+/// `descriptor`'s instance, which callers should pass back into
+/// [`get_memory_buffer`] to detect a stale view.
+pub fn get_memory_generation(env: JNIEnv, descriptor: jlong, name: jstring) -> Result<jlong, StringErr> {
+    let name = memory_export_name(&env, name)?;
+    Ok(*mem_generations().lock().unwrap().get(&(descriptor as usize, name)).unwrap_or(&0) as jlong)
+}
+
+/// Returns a zero-copy `java.nio.ByteBuffer` over the wasm instance's linear
+/// memory export `name` (falling back to `"memory"` when null/empty),
+/// avoiding the full copy that [`get_memory`]/[`set_memory`] pay on every
+/// call. Because `memory.grow` can reallocate the backing storage, the
+/// caller must pass the generation it last observed via
+/// [`get_memory_generation`] (or `-1` on first fetch, when there is nothing
+/// to compare against yet); a mismatch means the memory has grown since, the
+/// returned buffer would be dangling, and the caller must re-fetch a fresh
+/// view instead. `-1` is reserved for "no prior observation" rather than `0`
+/// because `0` is itself a real, commonly-observed generation (a memory that
+/// has never grown) and must still participate in the mismatch check.
+pub fn get_memory_buffer(env: JNIEnv, descriptor: jlong, name: jstring, expected_generation: jlong) -> Result<jobject, StringErr> {
+    unsafe {
+        let name = memory_export_name(&env, name)?;
+        let current_generation = *mem_generations().lock().unwrap().get(&(descriptor as usize, name.clone())).unwrap_or(&0) as jlong;
+        if expected_generation != -1 && expected_generation != current_generation {
+            return Err(StringErr("stale memory buffer: memory has grown since this view was fetched, re-fetch with get_memory_generation".into()));
+        }
+
+        let ins = crate::get_ins_by_id(descriptor as usize);
+        let mem = ins.0.exports.get_memory(&name)
+            .map_err(|_| StringErr(format!("no memory export named '{}'", name)))?;
+        let view = mem.view(&ins.1);
+        // Seed (or refresh) the tracked baseline size the moment a buffer is
+        // handed out, not just lazily on the next `execute`. Without this, a
+        // `memory.grow` happening during the very first `execute` after this
+        // buffer was fetched would have no prior baseline to compare
+        // against, the generation would never bump, and this buffer would
+        // silently dangle.
+        track_memory_growth(descriptor as usize, &name, view.data_size());
+        let data = view.data_unchecked_mut();
+        Ok(env.new_direct_byte_buffer(data.as_mut_ptr(), data.len())?.into_inner())
+    }
+}
+
+
+/// A single entry in the import table supplied by the caller: which wasm
+/// import `(module, name)` it satisfies, the Java static method that backs
+/// it, and the wasm signature used to marshal arguments/results for that
+/// method. This replaces dispatching every import through one variadic
+/// `onHostFunction(int, int, long[])` funnel keyed by an integer `host_id`.
+pub struct HostBinding {
+    pub module: String,
+    pub name: String,
+    pub class: String,
+    pub method: String,
+    pub descriptor: String,
+    pub sig: (Vec<Type>, Vec<Type>),
+}
+
+fn value_to_jvalue(v: &Value) -> Result<JValue<'static>, String> {
+    match v {
+        Value::I32(x) => Ok(JValue::Int(*x)),
+        Value::I64(x) => Ok(JValue::Long(*x)),
+        Value::F32(x) => Ok(JValue::Float(*x)),
+        Value::F64(x) => Ok(JValue::Double(*x)),
+        other => Err(format!("unsupported param type: {:?}", other)),
+    }
+}
+
+fn jvalue_to_value(ty: &Type, v: JValue) -> Result<Value, String> {
+    match (ty, v) {
+        (Type::I32, JValue::Int(x)) => Ok(Value::I32(x)),
+        (Type::I64, JValue::Long(x)) => Ok(Value::I64(x)),
+        (Type::F32, JValue::Float(x)) => Ok(Value::F32(x)),
+        (Type::F64, JValue::Double(x)) => Ok(Value::F64(x)),
+        (ty, v) => Err(format!("java return value {:?} does not match expected type {:?}", v, ty)),
+    }
+}
+
+fn jni_return_type(ret_types: &[Type]) -> Result<jni::signature::ReturnType, StringErr> {
+    use jni::signature::{Primitive, ReturnType};
+    match ret_types {
+        [] => Ok(ReturnType::Primitive(Primitive::Void)),
+        [Type::I32] => Ok(ReturnType::Primitive(Primitive::Int)),
+        [Type::I64] => Ok(ReturnType::Primitive(Primitive::Long)),
+        [Type::F32] => Ok(ReturnType::Primitive(Primitive::Float)),
+        [Type::F64] => Ok(ReturnType::Primitive(Primitive::Double)),
+        other => Err(StringErr(format!("unsupported host function return arity/type: {:?}", other))),
+    }
+}
+
+/// Builds a single host import `Function` for `binding`. The target class
+/// is resolved as a global ref and the `jmethodID` is resolved once, both up
+/// front, so the hot wasm->host call path never touches the JVM's
+/// string-based reflection: it only attaches the calling thread and invokes
+/// through the cached ids.
+///
+/// The closure below moves `method_id` into a `'static` `Send + Sync`
+/// wasmer host function, so this requires a `jni` release where
+/// `JStaticMethodID` is a plain `Copy` handle with no borrowed lifetime (true from
+/// `jni` 0.19 onward); an older `jni` whose `JStaticMethodID<'a>` borrows the
+/// defining `JNIEnv` cannot be cached across calls this way and this
+/// function would need to resolve the method by name on every invocation
+/// instead.
+fn create_host(store: &mut wasmer::Store, jvm: jni::JavaVM, descriptor: usize, binding: &HostBinding) -> Result<Function, StringErr> {
+    let host_function_signature = FunctionType::new(binding.sig.0.clone(), binding.sig.1.clone());
+    let ret_types = binding.sig.1.clone();
+    let ret_jni_type = jni_return_type(&ret_types)?;
+
+    let env = jvm.get_env()?;
+    let method_id = env.get_static_method_id(binding.class.as_str(), binding.method.as_str(), binding.descriptor.as_str())?;
+    let class_local = env.find_class(binding.class.as_str())?;
+    let class_ref = env.new_global_ref(class_local)?;
+    host_class_refs().lock().unwrap().entry(descriptor).or_default().push(class_ref.clone());
+
+    Ok(Function::new(store, &host_function_signature, move |args| {
+        // Callbacks can arrive from wasm threads the JVM has never seen
+        // before (e.g. a thread pool backing the guest's threading
+        // proposal); attach as a daemon so they don't crash instead of
+        // failing like a plain `get_env()` would.
+        let env: JNIEnv = as_rt!(jvm.attach_current_thread_as_daemon());
+        let jargs: Vec<JValue> = as_rt!(args.iter().map(value_to_jvalue).collect::<Result<_, _>>().map_err(RuntimeError::new));
+        let class: JClass = class_ref.as_obj().into();
+
+        let result = as_rt!(env.call_static_method_unchecked(class, method_id, ret_jni_type.clone(), &jargs));
+
+        match ret_types.as_slice() {
+            [] => Ok(vec![]),
+            [ty] => Ok(vec![as_rt!(jvalue_to_value(ty, result).map_err(RuntimeError::new))]),
+            _ => unreachable!("validated to arity <= 1 in jni_return_type"),
+        }
+    }))
+}
+
+/// Builds an `Imports` table from the caller-supplied `bindings`, one host
+/// `Function` per `(module, name)` import, registering the instance's
+/// cached class refs under `descriptor` so `close` can release them later.
+pub fn build_imports(store: &mut wasmer::Store, jvm: jni::JavaVM, descriptor: usize, bindings: &[HostBinding]) -> Result<Imports, StringErr> {
+    let mut imports = Imports::new();
+    for binding in bindings {
+        let function = create_host(store, jvm.clone(), descriptor, binding)?;
+        imports.define(&binding.module, &binding.name, function);
+    }
+    Ok(imports)
 }
 
 pub fn execute(
@@ -112,11 +431,106 @@ pub fn execute(
             return Err(StringErr("invalid params length".into()));
         }
 
-        let a = &fun.ty(&ins.1).params().convert(a)?;
-        let results = fun.call(&mut ins.1, a)
-            .map_err(|re| StringErr(format!("Got unexpected runtime error: {:?}", re)))?;
+        let param_types = fun.ty(&ins.1).params().to_vec();
+        let a = values_from_i64(&param_types, &a).map_err(StringErr)?;
+        let call_result = fun.call(&mut ins.1, &a);
+
+        // Track every exported memory, not just the conventionally-named
+        // `"memory"` one: a module can export several (multi-memory
+        // proposal) or export its single memory under another name, and a
+        // buffer handed out over any of them needs its own grow detection.
+        // This runs before the result is checked, so a call that grew memory
+        // and then trapped or ran out of gas still bumps the generation.
+        for (name, mem) in ins.0.exports.iter().memories() {
+            track_memory_growth(id as usize, name.as_str(), mem.view(&ins.1).data_size());
+        }
+
+        let results = match call_result {
+            Ok(results) => results,
+            Err(re) => {
+                // Only instances compiled with the metering middleware carry
+                // the global `get_remaining_points` reads; calling into it on
+                // a plain instance panics, so an ordinary trap on a
+                // non-metered instance must skip straight to the generic
+                // error below.
+                if is_metered(id as usize) {
+                    if let MeteringPoints::Exhausted = metering::get_remaining_points(&mut ins.1, &ins.0) {
+                        return Err(StringErr("out of gas".into()));
+                    }
+                }
+                return Err(StringErr(format!("Got unexpected runtime error: {:?}", re)));
+            }
+        };
+
+        let results = values_to_i64(&results).map_err(StringErr)?;
 
-        let results = as_i64_vec!(results, StringErr("unsupported return type".into()));
         return env.slice_to_jlong_array(&results);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_round_trip_through_i64() {
+        let types = vec![Type::I32, Type::I64, Type::F32, Type::F64];
+        let values = vec![Value::I32(-7), Value::I64(123456789), Value::F32(1.5), Value::F64(-2.25)];
+
+        let raw = values_to_i64(&values).unwrap();
+        let back = values_from_i64(&types, &raw).unwrap();
+
+        assert_eq!(values, back);
+    }
+
+    #[test]
+    fn values_to_i64_preserves_float_bits_instead_of_truncating() {
+        // A plain `as i64` cast on a float truncates toward zero; the wire
+        // format must instead carry the exact bit pattern so the other side
+        // reconstructs the same float.
+        let raw = values_to_i64(&[Value::F32(1.5), Value::F64(-2.25)]).unwrap();
+        assert_eq!(raw, vec![1.5f32.to_bits() as i64, (-2.25f64).to_bits() as i64]);
+    }
+
+    #[test]
+    fn values_from_i64_rejects_length_mismatch() {
+        let err = values_from_i64(&[Type::I32, Type::I32], &[1]).unwrap_err();
+        assert!(err.contains("expected 2 values, got 1"), "unexpected error: {}", err);
+    }
+
+    #[test]
+    fn default_if_empty_falls_back_to_memory() {
+        assert_eq!(default_if_empty(""), "memory");
+        assert_eq!(default_if_empty("custom"), "custom");
+    }
+
+    #[test]
+    fn track_memory_growth_only_bumps_generation_on_growth() {
+        // Distinct descriptor so this doesn't collide with other tests
+        // sharing the same process-global maps.
+        let descriptor = 0xdead_beef_usize;
+        let name = "test-memory";
+        let generation = || *mem_generations().lock().unwrap().get(&(descriptor, name.to_string())).unwrap_or(&0);
+
+        // First observation only seeds the baseline: nothing to have grown
+        // relative to yet, so this must NOT bump the generation. This is the
+        // case a stale-view bug hides in: if a buffer were considered valid
+        // before any baseline exists, a grow on the very next call would go
+        // undetected.
+        track_memory_growth(descriptor, name, 65536);
+        assert_eq!(generation(), 0);
+
+        // Unchanged size: still no growth.
+        track_memory_growth(descriptor, name, 65536);
+        assert_eq!(generation(), 0);
+
+        // Grown: the generation must bump so an outstanding buffer fetched
+        // under the old generation is seen as stale.
+        track_memory_growth(descriptor, name, 131072);
+        assert_eq!(generation(), 1);
+
+        remove_memory_tracking(descriptor);
+        assert!(mem_sizes().lock().unwrap().get(&(descriptor, name.to_string())).is_none());
+        assert!(mem_generations().lock().unwrap().get(&(descriptor, name.to_string())).is_none());
+    }
+}